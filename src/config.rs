@@ -0,0 +1,126 @@
+use serde::Deserialize;
+
+use crate::Error;
+
+/// Users, roles, and resources loaded from an entity definition file, mirroring
+/// how a Kubernetes cluster's users/RoleBindings/resources would be described
+/// outside of the policies themselves.
+#[derive(Debug, Deserialize)]
+pub struct EntityConfig {
+    pub users: Vec<UserConfig>,
+    pub roles: Vec<RoleConfig>,
+    pub resources: Vec<ResourceConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UserConfig {
+    pub id: String,
+    pub role: String,
+
+    /// Extra attributes (e.g. `department`, `teams`) policies can key conditions off.
+    #[serde(default)]
+    pub attrs: toml::Table,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RoleConfig {
+    pub id: String,
+
+    /// Parent roles this role inherits permissions from, forming a role hierarchy.
+    #[serde(default)]
+    pub parents: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ResourceConfig {
+    pub id: String,
+
+    /// Extra attributes (e.g. `namespace`) policies can key conditions off.
+    #[serde(default)]
+    pub attrs: toml::Table,
+}
+
+impl EntityConfig {
+    pub fn load(path: &str) -> std::result::Result<Self, Error> {
+        let src = std::fs::read_to_string(path)?;
+        Self::parse(&src)
+    }
+
+    fn parse(src: &str) -> std::result::Result<Self, Error> {
+        toml::from_str(src).map_err(|e| Error::Config(e.to_string()))
+    }
+
+    pub fn find_user(&self, id: &str) -> std::result::Result<&UserConfig, Error> {
+        self.users
+            .iter()
+            .find(|u| u.id == id)
+            .ok_or_else(|| Error::NoSuchRecord(id.to_string()))
+    }
+
+    // Not called yet (no CLI command looks up a role directly), kept for symmetry with find_user/find_resource.
+    #[allow(dead_code)]
+    pub fn find_role(&self, id: &str) -> std::result::Result<&RoleConfig, Error> {
+        self.roles
+            .iter()
+            .find(|r| r.id == id)
+            .ok_or_else(|| Error::NoSuchRecord(id.to_string()))
+    }
+
+    pub fn find_resource(&self, id: &str) -> std::result::Result<&ResourceConfig, Error> {
+        self.resources
+            .iter()
+            .find(|r| r.id == id)
+            .ok_or_else(|| Error::NoSuchRecord(id.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> EntityConfig {
+        EntityConfig::parse(
+            r#"
+            [[users]]
+            id = "alice"
+            role = "viewer"
+
+            [[roles]]
+            id = "viewer"
+
+            [[resources]]
+            id = "pod1"
+            "#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn parse_rejects_malformed_toml() {
+        let err = EntityConfig::parse("not = [valid").unwrap_err();
+        assert!(matches!(err, Error::Config(_)));
+    }
+
+    #[test]
+    fn find_user_returns_matching_record() {
+        assert_eq!(config().find_user("alice").unwrap().role, "viewer");
+    }
+
+    #[test]
+    fn find_user_errors_on_unknown_id() {
+        let err = config().find_user("bob").unwrap_err();
+        assert!(matches!(err, Error::NoSuchRecord(id) if id == "bob"));
+    }
+
+    #[test]
+    fn find_role_errors_on_unknown_id() {
+        let err = config().find_role("admin").unwrap_err();
+        assert!(matches!(err, Error::NoSuchRecord(id) if id == "admin"));
+    }
+
+    #[test]
+    fn find_resource_errors_on_unknown_id() {
+        let err = config().find_resource("pod2").unwrap_err();
+        assert!(matches!(err, Error::NoSuchRecord(id) if id == "pod2"));
+    }
+}
@@ -2,89 +2,226 @@ use std::collections::{HashMap, HashSet};
 use std::str::FromStr;
 
 use itertools::Itertools;
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use cedar_policy::{
-    Authorizer, Context, Decision, Diagnostics, Entities, Entity, EntityId, EntityTypeName, EntityUid, ParseErrors, PolicySet, Request,
-    Schema, SchemaError, ValidationMode, Validator,
+    Authorizer, Context, Decision, Entities, Entity, EntityId, EntityTypeName, EntityUid, ParseErrors, PartialResponse, PolicyId,
+    PolicySet, Request, RequestBuilder, RestrictedExpression, Schema, SchemaError, SlotId, ValidationMode, Validator,
 };
 use thiserror::Error;
 
+mod config;
+
+use config::EntityConfig;
+
 /// Sample rbac implementation with cedar
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
-    /// Principal
-    #[arg()]
-    principal: String,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Check whether a principal may perform an action on a resource
+    Check {
+        /// Principal
+        principal: String,
+
+        /// Action
+        action: String,
+
+        /// Resource: subject of info.
+        resource: String,
+
+        /// Link a policy template to a principal/resource pair, e.g.
+        /// `--bind pod-reader:Alice:nginx`. May be given more than once.
+        #[arg(long = "bind", value_name = "TEMPLATE:PRINCIPAL:RESOURCE")]
+        binds: Vec<String>,
+
+        /// Authorization context as a JSON object, e.g. `{"namespace": "prod"}`.
+        #[arg(long)]
+        context: Option<String>,
+    },
+    /// List every resource a principal may perform an action on
+    List {
+        /// Principal
+        principal: String,
 
-    /// Action
-    #[arg()]
-    action: String,
+        /// Action
+        action: String,
 
-    /// Resource: subject of info.
-    #[arg()]
-    resource: String,
+        /// Link a policy template to a principal/resource pair, e.g.
+        /// `--bind pod-reader:Alice:nginx`. May be given more than once.
+        #[arg(long = "bind", value_name = "TEMPLATE:PRINCIPAL:RESOURCE")]
+        binds: Vec<String>,
+
+        /// Authorization context as a JSON object, e.g. `{"namespace": "prod"}`.
+        #[arg(long)]
+        context: Option<String>,
+    },
+}
+
+/// A `--bind <template_id>:<principal>:<resource>` argument, naming a policy
+/// template to link against a concrete principal/resource pair — the Cedar
+/// equivalent of a Kubernetes RoleBinding.
+struct RoleBinding {
+    template_id: PolicyId,
+    principal: EntityUid,
+    resource: EntityUid,
 }
 
 struct User {
     id: String,
     role: String,
+    attrs: toml::Table,
 }
 
-impl From<User> for Entity {
-    fn from(value: User) -> Self {
-        let eid = EntityId::from_str(&value.id).unwrap();
-        let type_name = EntityTypeName::from_str("Kubernetes::User").unwrap();
+impl TryFrom<User> for Entity {
+    type Error = Error;
+
+    fn try_from(value: User) -> std::result::Result<Self, Self::Error> {
+        let eid = EntityId::from_str(&value.id).map_err(|e| Error::NoSuchRecord(e.to_string()))?;
+        let type_name = EntityTypeName::from_str("Kubernetes::User").map_err(|e| Error::NoSuchRecord(e.to_string()))?;
         let euid = EntityUid::from_type_name_and_id(type_name, eid);
-        let attrs = HashMap::new();
-        let parent_eid = EntityId::from_str(&value.role).unwrap();
-        let parent_type_name = EntityTypeName::from_str("Kubernetes::Role").unwrap();
+        let attrs = restricted_expressions_from_table(&value.attrs)?;
+        let parent_eid = EntityId::from_str(&value.role).map_err(|e| Error::NoSuchRecord(e.to_string()))?;
+        let parent_type_name = EntityTypeName::from_str("Kubernetes::Role").map_err(|e| Error::NoSuchRecord(e.to_string()))?;
         let parent_euid = EntityUid::from_type_name_and_id(parent_type_name, parent_eid);
         let parents = HashSet::from([parent_euid]);
-        Entity::new(euid, attrs, parents)
+        Ok(Entity::new(euid, attrs, parents))
     }
 }
 
 struct Role {
     id: String,
+    parents: Vec<String>,
 }
 
-impl From<Role> for Entity {
-    fn from(value: Role) -> Self {
-        let eid = EntityId::from_str(&value.id).unwrap();
-        let type_name = EntityTypeName::from_str("Kubernetes::Role").unwrap();
+impl TryFrom<Role> for Entity {
+    type Error = Error;
+
+    fn try_from(value: Role) -> std::result::Result<Self, Self::Error> {
+        let eid = EntityId::from_str(&value.id).map_err(|e| Error::NoSuchRecord(e.to_string()))?;
+        let type_name = EntityTypeName::from_str("Kubernetes::Role").map_err(|e| Error::NoSuchRecord(e.to_string()))?;
         let euid = EntityUid::from_type_name_and_id(type_name, eid);
         let attrs = HashMap::new();
-        let parents = HashSet::new();
-        Entity::new(euid, attrs, parents)
+        let parents = value
+            .parents
+            .iter()
+            .map(|parent| {
+                let parent_eid = EntityId::from_str(parent).map_err(|e| Error::NoSuchRecord(e.to_string()))?;
+                let parent_type_name = EntityTypeName::from_str("Kubernetes::Role").map_err(|e| Error::NoSuchRecord(e.to_string()))?;
+                Ok(EntityUid::from_type_name_and_id(parent_type_name, parent_eid))
+            })
+            .collect::<std::result::Result<HashSet<_>, Error>>()?;
+        Ok(Entity::new(euid, attrs, parents))
     }
 }
 
 struct Pod {
     id: String,
+    attrs: toml::Table,
 }
 
-impl From<Pod> for Entity {
-    fn from(value: Pod) -> Self {
-        let eid = EntityId::from_str(&value.id).unwrap();
-        let type_name = EntityTypeName::from_str("Kubernetes::Info").unwrap();
+impl TryFrom<Pod> for Entity {
+    type Error = Error;
+
+    fn try_from(value: Pod) -> std::result::Result<Self, Self::Error> {
+        let eid = EntityId::from_str(&value.id).map_err(|e| Error::NoSuchRecord(e.to_string()))?;
+        let type_name = EntityTypeName::from_str("Kubernetes::Info").map_err(|e| Error::NoSuchRecord(e.to_string()))?;
         let euid = EntityUid::from_type_name_and_id(type_name, eid);
-        let attrs = HashMap::new();
+        let attrs = restricted_expressions_from_table(&value.attrs)?;
         let parents = HashSet::new();
-        Entity::new(euid, attrs, parents)
+        Ok(Entity::new(euid, attrs, parents))
+    }
+}
+
+/// Convert a TOML table of entity attributes into the `RestrictedExpression`s
+/// Cedar needs to evaluate `when`/`unless` conditions over them.
+fn restricted_expressions_from_table(table: &toml::Table) -> std::result::Result<HashMap<String, RestrictedExpression>, Error> {
+    table
+        .iter()
+        .map(|(key, value)| Ok((key.clone(), restricted_expression_from_toml(value)?)))
+        .collect()
+}
+
+fn restricted_expression_from_toml(value: &toml::Value) -> std::result::Result<RestrictedExpression, Error> {
+    match value {
+        toml::Value::String(s) => Ok(RestrictedExpression::new_string(s.clone())),
+        toml::Value::Integer(i) => Ok(RestrictedExpression::new_long(*i)),
+        toml::Value::Boolean(b) => Ok(RestrictedExpression::new_bool(*b)),
+        toml::Value::Array(items) => {
+            let items = items.iter().map(restricted_expression_from_toml).collect::<std::result::Result<Vec<_>, _>>()?;
+            Ok(RestrictedExpression::new_set(items))
+        }
+        toml::Value::Table(fields) => {
+            let fields = fields
+                .iter()
+                .map(|(k, v)| Ok((k.clone(), restricted_expression_from_toml(v)?)))
+                .collect::<std::result::Result<Vec<_>, Error>>()?;
+            Ok(RestrictedExpression::new_record(fields))
+        }
+        toml::Value::Float(_) | toml::Value::Datetime(_) => {
+            Err(Error::Config("unsupported attribute type: expected string, number, bool, array, or table".to_string()))
+        }
     }
 }
 
 fn main() {
+    if let Err(e) = run() {
+        println!("{}", e);
+    }
+}
+
+fn run() -> std::result::Result<(), Error> {
     let args = Args::parse();
+    let config = EntityConfig::load("entities.toml")?;
+
+    match args.command {
+        Command::Check { principal, action, resource, binds, context } => {
+            let user = find_user_by_id(&config, &principal)?;
+            let pod = find_pod_by_id(&config, &resource)?;
+            let bindings = binds.iter().map(|b| parse_binding(&config, b)).collect::<std::result::Result<Vec<_>, _>>()?;
 
-    let principal = find_user_by_id(&args.principal).unwrap();
-    let resource = find_pod_by_id(&args.resource).unwrap();
+            match is_authorized(&config, &user.try_into()?, &action, &pod.try_into()?, &bindings, context.as_deref()) {
+                Ok(result) => println!("{}", result),
+                Err(e) => println!("{}", e),
+            }
+        }
+        Command::List { principal, action, binds, context } => {
+            let user = find_user_by_id(&config, &principal)?;
+            let bindings = binds.iter().map(|b| parse_binding(&config, b)).collect::<std::result::Result<Vec<_>, _>>()?;
 
-    match is_authorized(&principal.into(), &args.action, &resource.into()) {
-        Ok(_) => println!("Hello {}! You can {} {}.", args.principal, &args.action, &args.resource),
-        Err(e) => println!("{}", e),
+            match list_authorized(&config, &user.try_into()?, &action, &bindings, context.as_deref()) {
+                Ok(resources) => {
+                    for resource in resources {
+                        println!("{}", resource);
+                    }
+                }
+                Err(e) => println!("{}", e),
+            }
+        }
     }
+    Ok(())
+}
+
+/// Parse a `--bind <template_id>:<principal>:<resource>` argument into the
+/// `PolicyId`/`EntityUid`s needed to link a template.
+fn parse_binding(config: &EntityConfig, s: &str) -> std::result::Result<RoleBinding, Error> {
+    let (template_id, principal, resource) = s
+        .splitn(3, ':')
+        .collect_tuple()
+        .ok_or_else(|| Error::Request(format!("malformed --bind argument: {s}")))?;
+
+    let principal: Entity = find_user_by_id(config, principal)?.try_into()?;
+    let resource: Entity = find_pod_by_id(config, resource)?.try_into()?;
+
+    Ok(RoleBinding {
+        template_id: PolicyId::from_str(template_id).unwrap(),
+        principal: principal.uid(),
+        resource: resource.uid(),
+    })
 }
 
 #[derive(Debug, Error)]
@@ -93,8 +230,6 @@ pub enum Error {
     NoSuchRecord(String),
     #[error("No Such Entity: {0}")]
     NoSuchEntity(EntityUid),
-    #[error("Authorization Denied")]
-    AuthDenied(Diagnostics),
     #[error("The list {0} does not contain a task with id {1}")]
     InvalidTaskId(EntityUid, i64),
     #[error("Internal Error")]
@@ -109,60 +244,251 @@ pub enum Error {
     Schema(#[from] SchemaError),
     #[error("Validation Failed: {0}")]
     Validation(String),
+    #[error("Error Linking Template: {0}")]
+    Template(String),
+    #[error("Error Parsing Entity Config: {0}")]
+    Config(String),
+    #[error("Error Building Entity Store: {0}")]
+    Entities(String),
+    #[error("Error Parsing Context: {0}")]
+    Context(String),
 }
 
-fn find_user_by_id(id: &str) -> std::result::Result<User, Error> {
-    match id {
-        "Alice" => {
-            Ok(User { id: "Alice".to_string(), role: "admin".to_string() })
-        }
-        "Bob" => {
-            Ok(User { id: "Bob".to_string(), role: "viewer".to_string() })
-        }
-        _ => {
-            Err(Error::NoSuchRecord(id.to_string()))
-        }
-    }
+fn find_user_by_id(config: &EntityConfig, id: &str) -> std::result::Result<User, Error> {
+    let user = config.find_user(id)?;
+    Ok(User { id: user.id.clone(), role: user.role.clone(), attrs: user.attrs.clone() })
 }
 
-fn find_pod_by_id(id: &str) -> std::result::Result<Pod, Error> {
-    if id == "nginx-pod" {
-        Ok(Pod { id: "nginx-pod".to_string() })
-    } else {
-        Err(Error::NoSuchRecord(id.to_string()))
+fn find_pod_by_id(config: &EntityConfig, id: &str) -> std::result::Result<Pod, Error> {
+    let resource = config.find_resource(id)?;
+    Ok(Pod { id: resource.id.clone(), attrs: resource.attrs.clone() })
+}
+
+/// The outcome of an authorization check, including the policies that determined
+/// it and any evaluation errors encountered along the way.
+struct AuthorizationResult {
+    decision: Decision,
+    reasons: Vec<PolicyId>,
+    errors: Vec<String>,
+}
+
+impl std::fmt::Display for AuthorizationResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let reasons = self.reasons.iter().map(|id| format!("`{id}`")).join(", ");
+        match self.decision {
+            Decision::Allow if reasons.is_empty() => write!(f, "Allowed; no specific policy determined the decision."),
+            Decision::Allow => write!(f, "Allowed by policy {reasons}."),
+            Decision::Deny if reasons.is_empty() => write!(f, "Denied; no policy matched."),
+            Decision::Deny => write!(f, "Denied by policy {reasons}."),
+        }?;
+        if !self.errors.is_empty() {
+            write!(f, " Evaluation errors: {}", self.errors.iter().join("; "))?;
+        }
+        Ok(())
     }
 }
 
-fn is_authorized(principal: &Entity, action: &str, resource: &Entity) -> std::result::Result<(), Error> {
+fn is_authorized(
+    config: &EntityConfig,
+    principal: &Entity,
+    action: &str,
+    resource: &Entity,
+    bindings: &[RoleBinding],
+    context: Option<&str>,
+) -> std::result::Result<AuthorizationResult, Error> {
     let authorizer = Authorizer::new();
     let schema = get_schema()?;
-    let policies = get_policy_set(&schema)?;
-    let entities = get_entity_set();
-    let action_uid: EntityUid = format!(r#"Kubernetes::Action::"{action}""#).parse().unwrap();
+    let policies = get_policy_set(&schema, bindings)?;
+    let entities = get_entity_set(config)?;
+    let action_uid = get_action_uid(action)?;
+    let context = get_context(&schema, &action_uid, context)?;
     let q = Request::new(
         principal.uid().into(),
         action_uid.into(),
         resource.uid().into(),
-        Context::empty(),
+        context,
     );
     let response = authorizer.is_authorized(&q, &policies, &entities);
-    match response.decision() {
-        Decision::Allow => Ok(()),
-        Decision::Deny => Err(Error::AuthDenied(response.diagnostics().clone())),
+    Ok(AuthorizationResult {
+        decision: response.decision(),
+        reasons: response.diagnostics().reason().cloned().collect(),
+        errors: response.diagnostics().errors().map(|e| e.to_string()).collect(),
+    })
+}
+
+/// Parse a `--action` CLI argument into the `Kubernetes::Action` entity it names.
+fn get_action_uid(action: &str) -> std::result::Result<EntityUid, Error> {
+    format!(r#"Kubernetes::Action::"{action}""#)
+        .parse()
+        .map_err(|e: ParseErrors| Error::Request(format!("invalid action {action:?}: {e}")))
+}
+
+/// Parse and schema-validate an optional `--context` JSON argument.
+fn get_context(schema: &Schema, action: &EntityUid, context: Option<&str>) -> std::result::Result<Context, Error> {
+    match context {
+        Some(json) => {
+            let value: serde_json::Value = serde_json::from_str(json).map_err(|e| Error::Context(e.to_string()))?;
+            Context::from_json_value(value, Some((schema, action))).map_err(|e| Error::Context(e.to_string()))
+        }
+        None => Ok(Context::empty()),
+    }
+}
+
+/// Enumerate every `Kubernetes::Info` resource `principal` may `action` on,
+/// using partial evaluation to short-circuit the trivial all-allow/all-deny
+/// case before falling back to evaluating each candidate resource.
+fn list_authorized(
+    config: &EntityConfig,
+    principal: &Entity,
+    action: &str,
+    bindings: &[RoleBinding],
+    context: Option<&str>,
+) -> std::result::Result<Vec<EntityUid>, Error> {
+    let authorizer = Authorizer::new();
+    let schema = get_schema()?;
+    let policies = get_policy_set(&schema, bindings)?;
+    let entities = get_entity_set(config)?;
+    let action_uid = get_action_uid(action)?;
+    let context = get_context(&schema, &action_uid, context)?;
+
+    resolve_authorized_resources(&authorizer, &policies, &entities, principal, &action_uid, &context)
+}
+
+/// Run partial evaluation for `principal`/`action` with the resource left
+/// unknown. If that alone decides the outcome (no policy actually depended
+/// on which resource it was), every or no candidate is authorized and we
+/// can skip evaluating them individually.
+///
+/// Otherwise, some policy's decision genuinely depends on the resource, and
+/// we must check each candidate. We deliberately check it against the
+/// *original* `policies`, not the residual `is_authorized_partial` hands
+/// back: the residual bakes the unresolved resource into an opaque
+/// `unknown(resource)` term that the evaluator never reconnects to a later
+/// request's `resource` field (re-running `is_authorized`/`is_authorized_partial`
+/// against it with a concrete resource still comes back residual/errored).
+/// `cedar-policy` only exposes a way to resolve that substitution
+/// (`PartialResponse::reauthorize`) in newer major versions than the one
+/// pinned here, so falling back to the full policy set per candidate is the
+/// correct behavior available to us, not a missed optimization.
+fn resolve_authorized_resources(
+    authorizer: &Authorizer,
+    policies: &PolicySet,
+    entities: &Entities,
+    principal: &Entity,
+    action_uid: &EntityUid,
+    context: &Context,
+) -> std::result::Result<Vec<EntityUid>, Error> {
+    // Leave the resource unset (not `.resource(None)`, which would bind it to
+    // a concrete "unspecified" entity) so partial evaluation treats it as an
+    // unknown and hands back a residual instead of a concrete decision.
+    let q = RequestBuilder::default()
+        .principal(Some(principal.uid()))
+        .action(Some(action_uid.clone()))
+        .context(context.clone())
+        .build();
+
+    let response = authorizer.is_authorized_partial(&q, policies, entities);
+
+    match response {
+        PartialResponse::Concrete(response) => match response.decision() {
+            // No unknowns left at all: either every resource is allowed, or none is.
+            Decision::Allow => return Ok(resource_candidates(entities)),
+            Decision::Deny => return Ok(Vec::new()),
+        },
+        // Some policy's decision actually depends on the resource; check each one.
+        PartialResponse::Residual(_) => {}
+    }
+
+    let mut allowed = Vec::new();
+    for candidate in resource_candidates(entities) {
+        let candidate_q = Request::new(
+            principal.uid().into(),
+            action_uid.clone().into(),
+            candidate.clone().into(),
+            context.clone(),
+        );
+        let candidate_response = authorizer.is_authorized(&candidate_q, policies, entities);
+        if candidate_response.decision() == Decision::Allow {
+            allowed.push(candidate);
+        }
     }
+    Ok(allowed)
+}
+
+/// All `Kubernetes::Info` entities currently known to the entity store.
+fn resource_candidates(entities: &Entities) -> Vec<EntityUid> {
+    let info_type = EntityTypeName::from_str("Kubernetes::Info").unwrap();
+    entities
+        .iter()
+        .map(|entity| entity.uid())
+        .filter(|uid| uid.type_name() == &info_type)
+        .collect()
 }
 
 fn get_schema() -> std::result::Result<Schema, Error> {
     let schema_path = "k8s.cedarschema.json";
-    let schema_file = std::fs::File::open(&schema_path)?;
+    let schema_file = std::fs::File::open(schema_path)?;
     Ok(Schema::from_file(schema_file)?)
 }
 
-fn get_policy_set(schema: &Schema) -> std::result::Result<PolicySet, Error> {
-    let policies_path = "policies.cedar";
+/// Remap a `templates.cedar` id into a namespace disjoint from any id
+/// `policies.cedar` could use, so merging the two `PolicySet`s can't collide.
+fn namespaced_template_id(id: &PolicyId) -> PolicyId {
+    PolicyId::from_str(&format!("template:{id}")).unwrap()
+}
+
+/// Parse `policies.cedar`'s and (if present) `templates.cedar`'s sources and
+/// merge them into one `PolicySet`, linking any `--bind` role bindings.
+fn merge_policy_sources(
+    policy_src: &str,
+    templates_src: Option<&str>,
+    bindings: &[RoleBinding],
+) -> std::result::Result<PolicySet, Error> {
+    let mut policies: PolicySet = policy_src.parse()?;
+
+    if let Some(templates_src) = templates_src {
+        let templates: PolicySet = templates_src.parse()?;
+        for template in templates.templates() {
+            // `policies.cedar` and `templates.cedar` are parsed as independent
+            // `PolicySet`s, so Cedar assigns each its own positional ids
+            // ("policy0", "policy1", ...) starting from zero. Re-id every
+            // template under this namespace so it can never collide with a
+            // static policy's id, regardless of which ids either file uses.
+            let namespaced_id = namespaced_template_id(template.id());
+            policies
+                .add_template(template.new_id(namespaced_id))
+                .map_err(|e| Error::Template(e.to_string()))?;
+        }
+    }
+
+    for (i, binding) in bindings.iter().enumerate() {
+        let template_id = namespaced_template_id(&binding.template_id);
+        let linked_id = PolicyId::from_str(&format!("{template_id}-binding-{i}")).unwrap();
+        let vals = HashMap::from([
+            (SlotId::principal(), binding.principal.clone()),
+            (SlotId::resource(), binding.resource.clone()),
+        ]);
+        policies
+            .link(template_id, linked_id, vals)
+            .map_err(|e| Error::Template(e.to_string()))?;
+    }
+
+    Ok(policies)
+}
+
+fn get_policy_set(schema: &Schema, bindings: &[RoleBinding]) -> std::result::Result<PolicySet, Error> {
+    let policy_src = std::fs::read_to_string("policies.cedar")?;
+
+    // Templates are optional: a deployment that hasn't adopted `--bind` role
+    // bindings yet may not have a `templates.cedar` at all.
+    let templates_src = match std::fs::read_to_string("templates.cedar") {
+        Ok(src) => Some(src),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => return Err(Error::IO(e)),
+    };
+
+    let policies = merge_policy_sources(&policy_src, templates_src.as_deref(), bindings)?;
 
-    let policy_src = std::fs::read_to_string(&policies_path)?;
-    let policies = policy_src.parse()?;
     let validator = Validator::new(schema.clone());
     let output = validator.validate(&policies, ValidationMode::default());
 
@@ -177,17 +503,190 @@ fn get_policy_set(schema: &Schema) -> std::result::Result<PolicySet, Error> {
     }
 }
 
-fn get_entity_set() -> Entities {
-    let users = vec![
-        Entity::from(User { id: "Alice".to_string(), role: "admin".to_string() }),
-        Entity::from(User { id: "Bob".to_string(), role: "viewer".to_string() }),
-    ];
-    let roles = vec![
-        Entity::from(Role { id: "admin".to_string() }),
-    ];
-    let resources = vec![
-        Entity::from(Pod { id: "nginx".to_string() }),
-    ];
+fn get_entity_set(config: &EntityConfig) -> std::result::Result<Entities, Error> {
+    let users = config
+        .users
+        .iter()
+        .map(|u| Entity::try_from(User { id: u.id.clone(), role: u.role.clone(), attrs: u.attrs.clone() }))
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    let roles = config
+        .roles
+        .iter()
+        .map(|r| Entity::try_from(Role { id: r.id.clone(), parents: r.parents.clone() }))
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    let resources = config
+        .resources
+        .iter()
+        .map(|r| Entity::try_from(Pod { id: r.id.clone(), attrs: r.attrs.clone() }))
+        .collect::<std::result::Result<Vec<_>, _>>()?;
     let all = [users, roles, resources].concat();
-    Entities::from_entities(all).unwrap()
+    Entities::from_entities(all).map_err(|e| Error::Entities(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entities() -> Entities {
+        let alice: EntityUid = r#"Kubernetes::User::"alice""#.parse().unwrap();
+        let pod1: EntityUid = r#"Kubernetes::Info::"pod1""#.parse().unwrap();
+        let pod2: EntityUid = r#"Kubernetes::Info::"pod2""#.parse().unwrap();
+        Entities::from_entities([
+            Entity::new(alice, HashMap::new(), HashSet::new()),
+            Entity::new(pod1, HashMap::new(), HashSet::new()),
+            Entity::new(pod2, HashMap::new(), HashSet::new()),
+        ])
+        .unwrap()
+    }
+
+    fn principal_and_action() -> (Entity, EntityUid) {
+        let principal = Entity::new(r#"Kubernetes::User::"alice""#.parse().unwrap(), HashMap::new(), HashSet::new());
+        let action_uid: EntityUid = r#"Kubernetes::Action::"get""#.parse().unwrap();
+        (principal, action_uid)
+    }
+
+    #[test]
+    fn resolve_authorized_resources_short_circuits_on_concrete_allow() {
+        let policies: PolicySet = r#"permit(principal, action == Kubernetes::Action::"get", resource);"#.parse().unwrap();
+        let entities = entities();
+        let (principal, action_uid) = principal_and_action();
+        let authorizer = Authorizer::new();
+
+        let allowed = resolve_authorized_resources(&authorizer, &policies, &entities, &principal, &action_uid, &Context::empty()).unwrap();
+
+        let mut allowed: Vec<String> = allowed.iter().map(|uid| uid.to_string()).collect();
+        allowed.sort();
+        assert_eq!(allowed, vec![r#"Kubernetes::Info::"pod1""#, r#"Kubernetes::Info::"pod2""#]);
+    }
+
+    #[test]
+    fn resolve_authorized_resources_filters_residual_per_candidate() {
+        let policies: PolicySet = r#"
+            permit(principal, action == Kubernetes::Action::"get", resource)
+            when { resource == Kubernetes::Info::"pod1" };
+        "#
+        .parse()
+        .unwrap();
+        let entities = entities();
+        let (principal, action_uid) = principal_and_action();
+        let authorizer = Authorizer::new();
+
+        let allowed = resolve_authorized_resources(&authorizer, &policies, &entities, &principal, &action_uid, &Context::empty()).unwrap();
+
+        assert_eq!(allowed, vec![r#"Kubernetes::Info::"pod1""#.parse::<EntityUid>().unwrap()]);
+    }
+
+    #[test]
+    fn resolve_authorized_resources_empty_on_concrete_deny() {
+        let policies: PolicySet = PolicySet::new();
+        let entities = entities();
+        let (principal, action_uid) = principal_and_action();
+        let authorizer = Authorizer::new();
+
+        let allowed = resolve_authorized_resources(&authorizer, &policies, &entities, &principal, &action_uid, &Context::empty()).unwrap();
+
+        assert!(allowed.is_empty());
+    }
+
+    #[test]
+    fn merge_policy_sources_namespaces_default_template_ids() {
+        // Both files use Cedar's default positional id ("policy0") for their
+        // one statement, the common case a `--bind` operator would hit.
+        let policy_src = r#"forbid(principal, action, resource) when { false };"#;
+        let templates_src = r#"permit(principal == ?principal, action, resource == ?resource);"#;
+        let (principal, action_uid) = principal_and_action();
+        let pod1: EntityUid = r#"Kubernetes::Info::"pod1""#.parse().unwrap();
+        let bindings = [RoleBinding {
+            template_id: PolicyId::from_str("policy0").unwrap(),
+            principal: principal.uid(),
+            resource: pod1.clone(),
+        }];
+
+        let policies = merge_policy_sources(policy_src, Some(templates_src), &bindings).unwrap();
+
+        let entities = entities();
+        let authorizer = Authorizer::new();
+        let q = Request::new(principal.uid().into(), action_uid.into(), pod1.into(), Context::empty());
+        let response = authorizer.is_authorized(&q, &policies, &entities);
+
+        assert_eq!(response.decision(), Decision::Allow);
+    }
+
+    #[test]
+    fn restricted_expressions_from_table_converts_every_supported_type() {
+        let table: toml::Table = toml::from_str(
+            r#"
+            team = "infra"
+            count = 3
+            active = true
+            tags = ["a", "b"]
+            meta = { k = "v" }
+            "#,
+        )
+        .unwrap();
+        let attrs = restricted_expressions_from_table(&table).unwrap();
+
+        let principal_uid: EntityUid = r#"Kubernetes::User::"alice""#.parse().unwrap();
+        let principal = Entity::new(principal_uid.clone(), attrs, HashSet::new());
+        let resource_uid: EntityUid = r#"Kubernetes::Info::"pod1""#.parse().unwrap();
+        let resource = Entity::new(resource_uid.clone(), HashMap::new(), HashSet::new());
+        let entities = Entities::from_entities([principal, resource]).unwrap();
+
+        let policies: PolicySet = r#"
+            permit(principal, action == Kubernetes::Action::"get", resource)
+            when {
+                principal.team == "infra" &&
+                principal.count == 3 &&
+                principal.active &&
+                principal.tags.contains("a") &&
+                principal.meta.k == "v"
+            };
+        "#
+        .parse()
+        .unwrap();
+
+        let authorizer = Authorizer::new();
+        let action_uid: EntityUid = r#"Kubernetes::Action::"get""#.parse().unwrap();
+        let q = Request::new(principal_uid.into(), action_uid.into(), resource_uid.into(), Context::empty());
+        let response = authorizer.is_authorized(&q, &policies, &entities);
+
+        assert_eq!(response.decision(), Decision::Allow);
+    }
+
+    #[test]
+    fn restricted_expression_from_toml_rejects_float() {
+        let err = restricted_expression_from_toml(&toml::Value::Float(1.5)).unwrap_err();
+        assert!(matches!(err, Error::Config(_)));
+    }
+
+    #[test]
+    fn authorization_result_display_without_reasons() {
+        let allow = AuthorizationResult { decision: Decision::Allow, reasons: Vec::new(), errors: Vec::new() };
+        assert_eq!(allow.to_string(), "Allowed; no specific policy determined the decision.");
+
+        let deny = AuthorizationResult { decision: Decision::Deny, reasons: Vec::new(), errors: Vec::new() };
+        assert_eq!(deny.to_string(), "Denied; no policy matched.");
+    }
+
+    #[test]
+    fn authorization_result_display_with_reasons() {
+        let reasons = vec![PolicyId::from_str("policy0").unwrap()];
+
+        let allow = AuthorizationResult { decision: Decision::Allow, reasons: reasons.clone(), errors: Vec::new() };
+        assert_eq!(allow.to_string(), "Allowed by policy `policy0`.");
+
+        let deny = AuthorizationResult { decision: Decision::Deny, reasons, errors: Vec::new() };
+        assert_eq!(deny.to_string(), "Denied by policy `policy0`.");
+    }
+
+    #[test]
+    fn authorization_result_display_appends_evaluation_errors() {
+        let result = AuthorizationResult {
+            decision: Decision::Deny,
+            reasons: Vec::new(),
+            errors: vec!["while evaluating policy0: type error".to_string()],
+        };
+
+        assert_eq!(result.to_string(), "Denied; no policy matched. Evaluation errors: while evaluating policy0: type error");
+    }
 }